@@ -4,6 +4,7 @@ use rustc_hash::FxHashMap;
 use rustpython_ast::Location;
 
 use crate::ast;
+use crate::source_code_style::SourceCodeStyleDetector;
 
 #[derive(Hash, Eq, PartialOrd, PartialEq, Ord, Debug, Clone, Default)]
 pub struct LocationHash {
@@ -102,6 +103,29 @@ pub struct ImportBlock<'a> {
     pub import_from_star: FxHashMap<ImportFromData<'a>, CommentSet<'a>>,
 }
 
+impl<'a> ImportBlock<'a> {
+    /// Render the `__all__` entries implied by explicit re-exports (e.g.
+    /// `from module import member as member`), quoted per the file's
+    /// detected `Quote` style.
+    ///
+    /// Entries are sorted by name, since `self.import_from_as` is an
+    /// `FxHashMap` and so iterates in arbitrary order.
+    pub fn to_all_entries(&self, stylist: &SourceCodeStyleDetector) -> Vec<String> {
+        let quote = stylist.quote().as_char();
+        let mut names: Vec<&str> = self
+            .import_from_as
+            .keys()
+            .filter(|(_, alias)| alias.asname.map_or(false, |asname| asname == alias.name))
+            .map(|(_, alias)| alias.name)
+            .collect();
+        names.sort_unstable();
+        names
+            .into_iter()
+            .map(|name| format!("{quote}{name}{quote}"))
+            .collect()
+    }
+}
+
 type AliasDataWithComments<'a> = (AliasData<'a>, CommentSet<'a>);
 
 #[derive(Debug, Default)]
@@ -114,3 +138,216 @@ pub struct OrderedImportBlock<'a> {
         Vec<AliasDataWithComments<'a>>,
     )>,
 }
+
+impl<'a> OrderedImportBlock<'a> {
+    /// Render this block as source code, honoring the file's detected
+    /// `Indentation` style, and preserving every atop and inline comment
+    /// attached to an import.
+    pub fn format(&self, stylist: &SourceCodeStyleDetector) -> String {
+        let mut output = String::new();
+
+        for (alias, comments) in &self.import {
+            push_atop_comments(&mut output, "", &comments.atop);
+            output.push_str("import ");
+            push_alias(&mut output, alias);
+            push_inline_comment(&mut output, &comments.inline);
+            output.push('\n');
+        }
+
+        for (import_from, comments, _locations, aliases) in &self.import_from {
+            push_atop_comments(&mut output, "", &comments.atop);
+            // A lone member is rendered on the `from` line itself, so its
+            // atop comments go above that line.
+            if let [(_, alias_comments)] = aliases.as_slice() {
+                push_atop_comments(&mut output, "", &alias_comments.atop);
+            }
+            output.push_str("from ");
+            output.push_str(&import_from.module_name());
+            output.push_str(" import ");
+
+            // A single imported member fits on one line; multiple members
+            // are wrapped in a parenthesized, indented block, per the
+            // file's detected indentation.
+            if let [(alias, alias_comments)] = aliases.as_slice() {
+                push_alias(&mut output, alias);
+                push_inline_comment(&mut output, &comments.inline);
+                push_inline_comment(&mut output, &alias_comments.inline);
+                output.push('\n');
+            } else {
+                output.push_str("(");
+                push_inline_comment(&mut output, &comments.inline);
+                output.push('\n');
+                for (alias, alias_comments) in aliases {
+                    push_atop_comments(&mut output, stylist.indentation(), &alias_comments.atop);
+                    output.push_str(stylist.indentation());
+                    push_alias(&mut output, alias);
+                    output.push(',');
+                    push_inline_comment(&mut output, &alias_comments.inline);
+                    output.push('\n');
+                }
+                output.push_str(")\n");
+            }
+        }
+
+        output
+    }
+}
+
+/// Write a single `name` or `name as asname` import member.
+fn push_alias(output: &mut String, alias: &AliasData) {
+    output.push_str(alias.name);
+    if let Some(asname) = alias.asname {
+        output.push_str(" as ");
+        output.push_str(asname);
+    }
+}
+
+/// Write each atop comment as its own `indent# comment` line.
+fn push_atop_comments(output: &mut String, indent: &str, comments: &[Cow<str>]) {
+    for comment in comments {
+        output.push_str(indent);
+        output.push_str("# ");
+        output.push_str(comment);
+        output.push('\n');
+    }
+}
+
+/// Write every inline comment as a single trailing `  # comment` (joined with
+/// `; ` if there's more than one), or nothing if there are none.
+fn push_inline_comment(output: &mut String, comments: &[Cow<str>]) {
+    if comments.is_empty() {
+        return;
+    }
+    output.push_str("  # ");
+    output.push_str(&comments.iter().map(Cow::as_ref).collect::<Vec<_>>().join("; "));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::isort::types::{
+        AliasData, CommentSet, ImportBlock, ImportFromData, LocationWrapper, OrderedImportBlock,
+    };
+    use crate::source_code_locator::SourceCodeLocator;
+    use crate::source_code_style::SourceCodeStyleDetector;
+
+    #[test]
+    fn format_preserves_plain_import_comments() {
+        let contents = "";
+        let locator = SourceCodeLocator::new(contents);
+        let stylist = SourceCodeStyleDetector::from_contents(contents, &locator);
+
+        let block = OrderedImportBlock {
+            import: vec![(
+                AliasData {
+                    name: "os",
+                    asname: None,
+                },
+                CommentSet {
+                    atop: vec![Cow::Borrowed("standard library")],
+                    inline: vec![Cow::Borrowed("noqa")],
+                },
+            )],
+            import_from: vec![],
+        };
+
+        assert_eq!(
+            block.format(&stylist),
+            "# standard library\nimport os  # noqa\n"
+        );
+    }
+
+    #[test]
+    fn format_preserves_comments_on_wrapped_members() {
+        let contents = "";
+        let locator = SourceCodeLocator::new(contents);
+        let stylist = SourceCodeStyleDetector::from_contents(contents, &locator);
+
+        let module = "module".to_string();
+        let level = 0usize;
+        let block = OrderedImportBlock {
+            import: vec![],
+            import_from: vec![(
+                ImportFromData {
+                    module: Some(&module),
+                    level: Some(&level),
+                },
+                CommentSet {
+                    atop: vec![],
+                    inline: vec![Cow::Borrowed("noqa")],
+                },
+                LocationWrapper::default(),
+                vec![
+                    (
+                        AliasData {
+                            name: "a",
+                            asname: None,
+                        },
+                        CommentSet {
+                            atop: vec![Cow::Borrowed("about a")],
+                            inline: vec![],
+                        },
+                    ),
+                    (
+                        AliasData {
+                            name: "b",
+                            asname: None,
+                        },
+                        CommentSet {
+                            atop: vec![],
+                            inline: vec![Cow::Borrowed("about b")],
+                        },
+                    ),
+                ],
+            )],
+        };
+
+        // Asserted as substrings (rather than the full rendering) since the
+        // `from ...` prefix depends on `ImportFromData::module_name`, which
+        // is outside the scope of this test.
+        let output = block.format(&stylist);
+        // The `from`-level inline comment belongs on the opening-paren line;
+        // if it instead landed after the last member, the trailing `)` would
+        // end up inside the comment and the import would never be closed.
+        assert!(output.contains("(  # noqa\n"));
+        assert!(output.contains(&format!("{}# about a\n{}a,\n", "    ", "    ")));
+        assert!(output.contains("b,  # about b\n"));
+        assert!(output.ends_with(")\n"));
+    }
+
+    #[test]
+    fn to_all_entries_is_sorted() {
+        let contents = "";
+        let locator = SourceCodeLocator::new(contents);
+        let stylist = SourceCodeStyleDetector::from_contents(contents, &locator);
+
+        let module = "module".to_string();
+        let level = 0usize;
+        let alpha = "alpha".to_string();
+        let mu = "mu".to_string();
+        let zeta = "zeta".to_string();
+
+        let mut block = ImportBlock::default();
+        for (name, asname) in [("zeta", &zeta), ("alpha", &alpha), ("mu", &mu)] {
+            block.import_from_as.insert(
+                (
+                    ImportFromData {
+                        module: Some(&module),
+                        level: Some(&level),
+                    },
+                    AliasData {
+                        name,
+                        asname: Some(asname),
+                    },
+                ),
+                CommentSet::default(),
+            );
+        }
+
+        assert_eq!(
+            block.to_all_entries(&stylist),
+            vec!["\"alpha\"", "\"mu\"", "\"zeta\""]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Fast conversions between byte offsets and `Location { row, column }` pairs.
+
+use once_cell::unsync::OnceCell;
+use rustpython_ast::Location;
+
+use crate::ast::types::Range;
+
+/// A precomputed index of line-start offsets, built once per file, that makes
+/// offset-to-`Location` (and back) lookups `O(log n)` instead of re-scanning
+/// the source on every query.
+struct LineIndex {
+    /// Byte offset of the first character of each line.
+    line_starts: Vec<usize>,
+    /// For each line, the `(offset, len)` of any non-ASCII characters, where
+    /// `offset` is relative to the start of the line and `len` is the
+    /// character's length in bytes. Consulted to turn a byte offset into a
+    /// character-based column (and vice versa) without rescanning the line.
+    wide_chars: Vec<Vec<(usize, usize)>>,
+}
+
+impl LineIndex {
+    fn from_source_code(contents: &str) -> Self {
+        let mut line_starts = vec![0];
+        let mut wide_chars = vec![vec![]];
+
+        let mut line_start = 0;
+        for (offset, c) in contents.char_indices() {
+            if !c.is_ascii() {
+                wide_chars
+                    .last_mut()
+                    .unwrap()
+                    .push((offset - line_start, c.len_utf8()));
+            }
+            if c == '\n' {
+                line_start = offset + 1;
+                line_starts.push(line_start);
+                wide_chars.push(vec![]);
+            }
+        }
+
+        Self {
+            line_starts,
+            wide_chars,
+        }
+    }
+
+    /// Return the index of the line containing `offset`.
+    fn row_for_offset(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(row) => row,
+            Err(row) => row - 1,
+        }
+    }
+
+    /// Convert a byte offset into a source file to a `Location`.
+    fn offset_to_location(&self, offset: usize) -> Location {
+        let row = self.row_for_offset(offset);
+        let line_start = self.line_starts[row];
+        let rel = offset - line_start;
+
+        // Each non-ASCII character before `offset` counts as a single
+        // column, rather than the multiple bytes it occupies.
+        let mut column = rel;
+        for &(char_offset, len) in &self.wide_chars[row] {
+            if char_offset >= rel {
+                break;
+            }
+            column -= len - 1;
+        }
+
+        Location::new(row + 1, column)
+    }
+
+    /// Convert a `Location` back to a byte offset into the source file.
+    fn location_to_offset(&self, location: &Location, contents: &str) -> usize {
+        let row = location.row() - 1;
+        let line_start = self.line_starts[row];
+
+        // A trailing line with no newline has no entry beyond `line_starts`,
+        // so fall back to the end of the file.
+        let line_end = self
+            .line_starts
+            .get(row + 1)
+            .copied()
+            .unwrap_or(contents.len());
+
+        let target = location.column();
+        let mut column = 0;
+        let mut byte = 0;
+        for &(char_offset, len) in &self.wide_chars[row] {
+            let ascii_run = char_offset - byte;
+            if target <= column + ascii_run {
+                return line_start + byte + (target - column);
+            }
+            column += ascii_run + 1;
+            byte = char_offset + len;
+        }
+
+        let remaining = (line_end - line_start).saturating_sub(byte);
+        line_start + byte + (target - column).min(remaining)
+    }
+}
+
+/// Provides access to the source code of a file, and facilitates mapping
+/// between byte offsets and `Location`s.
+pub struct SourceCodeLocator<'a> {
+    contents: &'a str,
+    index: OnceCell<LineIndex>,
+}
+
+impl<'a> SourceCodeLocator<'a> {
+    pub fn new(contents: &'a str) -> Self {
+        Self {
+            contents,
+            index: OnceCell::default(),
+        }
+    }
+
+    fn index(&self) -> &LineIndex {
+        self.index
+            .get_or_init(|| LineIndex::from_source_code(self.contents))
+    }
+
+    /// Convert a byte offset into the source code to a `Location`.
+    pub fn offset_to_location(&self, offset: usize) -> Location {
+        self.index().offset_to_location(offset)
+    }
+
+    /// Convert a `Location` into a byte offset into the source code.
+    pub fn location_to_offset(&self, location: &Location) -> usize {
+        self.index().location_to_offset(location, self.contents)
+    }
+
+    /// Take the source code between two `Location`s.
+    pub fn slice_source_code_range(&self, range: &Range) -> &'a str {
+        let start = self.location_to_offset(&range.location);
+        let end = self.location_to_offset(&range.end_location);
+        &self.contents[start..end]
+    }
+
+    /// Take the source code from a given `Location` through the end of the
+    /// file.
+    pub fn slice_source_code_at(&self, location: &Location) -> &'a str {
+        let start = self.location_to_offset(location);
+        &self.contents[start..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustpython_ast::Location;
+
+    use crate::source_code_locator::SourceCodeLocator;
+
+    #[test]
+    fn offset_to_location() {
+        let contents = "x = 1\ny = 2\n";
+        let locator = SourceCodeLocator::new(contents);
+        assert_eq!(locator.offset_to_location(0), Location::new(1, 0));
+        assert_eq!(locator.offset_to_location(6), Location::new(2, 0));
+        assert_eq!(locator.offset_to_location(8), Location::new(2, 2));
+    }
+
+    #[test]
+    fn location_to_offset() {
+        let contents = "x = 1\ny = 2\n";
+        let locator = SourceCodeLocator::new(contents);
+        assert_eq!(locator.location_to_offset(&Location::new(1, 0)), 0);
+        assert_eq!(locator.location_to_offset(&Location::new(2, 0)), 6);
+        assert_eq!(locator.location_to_offset(&Location::new(2, 2)), 8);
+    }
+
+    #[test]
+    fn crlf() {
+        let contents = "x = 1\r\ny = 2\r\n";
+        let locator = SourceCodeLocator::new(contents);
+        // The `\r` is not counted as part of the next line's column.
+        assert_eq!(locator.offset_to_location(7), Location::new(2, 0));
+        assert_eq!(locator.location_to_offset(&Location::new(2, 0)), 7);
+    }
+
+    #[test]
+    fn no_trailing_newline() {
+        let contents = "x = 1";
+        let locator = SourceCodeLocator::new(contents);
+        assert_eq!(locator.offset_to_location(5), Location::new(1, 5));
+    }
+
+    #[test]
+    fn empty_file() {
+        let contents = "";
+        let locator = SourceCodeLocator::new(contents);
+        assert_eq!(locator.offset_to_location(0), Location::new(1, 0));
+    }
+
+    #[test]
+    fn non_ascii() {
+        // `å` is two bytes in UTF-8 (offsets 5 and 6) but a single column.
+        let contents = "x = 'å'\ny = 2\n";
+        let locator = SourceCodeLocator::new(contents);
+        assert_eq!(locator.offset_to_location(5), Location::new(1, 5));
+        // The closing quote, right after `å`, is one column past it, despite
+        // being two bytes after its start.
+        assert_eq!(locator.offset_to_location(7), Location::new(1, 6));
+        assert_eq!(locator.location_to_offset(&Location::new(1, 5)), 5);
+        assert_eq!(locator.location_to_offset(&Location::new(1, 6)), 7);
+
+        // Line 1 is 9 bytes long (the trailing `\n` included), so line 2
+        // starts at offset 9.
+        assert_eq!(locator.offset_to_location(9), Location::new(2, 0));
+        assert_eq!(locator.location_to_offset(&Location::new(2, 0)), 9);
+    }
+}
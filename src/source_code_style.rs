@@ -3,6 +3,7 @@
 use std::ops::Deref;
 
 use once_cell::unsync::OnceCell;
+use rustc_hash::FxHashMap;
 use rustpython_ast::Location;
 use rustpython_parser::lexer;
 use rustpython_parser::lexer::Tok;
@@ -15,33 +16,109 @@ use crate::vendor;
 pub struct SourceCodeStyleDetector<'a> {
     contents: &'a str,
     locator: &'a SourceCodeLocator<'a>,
-    indentation: OnceCell<Indentation>,
-    quote: OnceCell<Quote>,
+    indentation: OnceCell<StyleProfile<Indentation>>,
+    quote: OnceCell<StyleProfile<Quote>>,
+    line_ending: OnceCell<LineEnding>,
+    quote_preferences: OnceCell<QuotePreferences>,
 }
 
 impl<'a> SourceCodeStyleDetector<'a> {
     pub fn indentation(&'a self) -> &'a Indentation {
+        self.indentation_profile().value()
+    }
+
+    pub fn indentation_profile(&'a self) -> &'a StyleProfile<Indentation> {
         self.indentation
             .get_or_init(|| detect_indentation(self.contents, self.locator).unwrap_or_default())
     }
 
     pub fn quote(&'a self) -> &'a Quote {
+        self.quote_profile().value()
+    }
+
+    pub fn quote_profile(&'a self) -> &'a StyleProfile<Quote> {
         self.quote
             .get_or_init(|| detect_quote(self.contents, self.locator).unwrap_or_default())
     }
 
+    pub fn line_ending(&'a self) -> &'a LineEnding {
+        self.line_ending
+            .get_or_init(|| detect_line_ending(self.contents).unwrap_or_default())
+    }
+
+    /// The preferred quote style for inline (non-triple-quoted,
+    /// non-docstring) strings.
+    pub fn inline_quote(&'a self) -> &'a Quote {
+        self.quote_preferences().inline.value()
+    }
+
+    /// The preferred quote style for triple-quoted strings that aren't
+    /// docstrings.
+    pub fn multiline_quote(&'a self) -> &'a Quote {
+        self.quote_preferences().multiline.value()
+    }
+
+    /// The preferred quote style for docstrings.
+    pub fn docstring_quote(&'a self) -> &'a Quote {
+        self.quote_preferences().docstring.value()
+    }
+
+    fn quote_preferences(&'a self) -> &'a QuotePreferences {
+        self.quote_preferences
+            .get_or_init(|| detect_quote_preferences(self.contents, self.locator))
+    }
+
     pub fn from_contents(contents: &'a str, locator: &'a SourceCodeLocator<'a>) -> Self {
         Self {
             contents,
             locator,
             indentation: OnceCell::default(),
             quote: OnceCell::default(),
+            line_ending: OnceCell::default(),
+            quote_preferences: OnceCell::default(),
+        }
+    }
+}
+
+/// The result of a frequency-based style detection: the most common style
+/// observed, along with how often it (and any samples overall) occurred.
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct StyleProfile<T> {
+    value: T,
+    count: usize,
+    total: usize,
+}
+
+impl<T> StyleProfile<T> {
+    /// The detected style.
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// The number of samples that agreed with the detected style.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The total number of samples observed.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The proportion of samples that agreed with the detected style, as a
+    /// value between `0.0` and `1.0`. Callers can use this to decide whether
+    /// the signal is strong enough to act on.
+    pub fn confidence(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.count as f64 / self.total as f64
         }
     }
 }
 
 /// The quotation style used in Python source code.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub enum Quote {
     Single,
     Double,
@@ -53,6 +130,16 @@ impl Default for Quote {
     }
 }
 
+impl Quote {
+    /// The character used to delimit a string literal in this style.
+    pub fn as_char(&self) -> char {
+        match self {
+            Quote::Single => '\'',
+            Quote::Double => '"',
+        }
+    }
+}
+
 impl From<&Quote> for vendor::str::Quote {
     fn from(val: &Quote) -> Self {
         match val {
@@ -63,7 +150,7 @@ impl From<&Quote> for vendor::str::Quote {
 }
 
 /// The indentation style used in Python source code.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub struct Indentation(String);
 
 impl Indentation {
@@ -86,23 +173,202 @@ impl Deref for Indentation {
     }
 }
 
-/// Detect the indentation style of the given tokens.
-fn detect_indentation(contents: &str, locator: &SourceCodeLocator) -> Option<Indentation> {
+/// Independent quote-style preferences for the three distinct classes of
+/// string literal: inline, multiline (triple-quoted, non-docstring), and
+/// docstring.
+#[derive(Debug, Default)]
+struct QuotePreferences {
+    inline: StyleProfile<Quote>,
+    multiline: StyleProfile<Quote>,
+    docstring: StyleProfile<Quote>,
+}
+
+/// Detect the quote-style preferences for each class of string literal
+/// (inline, multiline, docstring), by classifying every `Tok::String` via its
+/// leading-quote pattern and, for docstrings, whether it's the first
+/// statement in a module, class, or function body.
+fn detect_quote_preferences(contents: &str, locator: &SourceCodeLocator) -> QuotePreferences {
+    let mut inline: FxHashMap<Quote, usize> = FxHashMap::default();
+    let mut multiline: FxHashMap<Quote, usize> = FxHashMap::default();
+    let mut docstring: FxHashMap<Quote, usize> = FxHashMap::default();
+
+    // Tracks whether the next token is the first statement of the module (or
+    // of a class/function body), and is thus eligible to be a docstring.
+    let mut expect_docstring = true;
+    // Tracks whether we're still inside a `def`/`class` header, waiting for
+    // the `Indent` that opens its body.
+    let mut saw_def_or_class = false;
+
+    for (start, tok, end) in lexer::make_tokenizer(contents).flatten() {
+        match tok {
+            Tok::Def | Tok::Class => {
+                saw_def_or_class = true;
+            }
+            Tok::Indent { .. } => {
+                expect_docstring = saw_def_or_class;
+                saw_def_or_class = false;
+            }
+            Tok::String { .. } => {
+                let content = locator.slice_source_code_range(&Range {
+                    location: start,
+                    end_location: end,
+                });
+                if let Some(pattern) = leading_quote(&content) {
+                    let quote = if pattern.contains('\'') {
+                        Quote::Single
+                    } else if pattern.contains('"') {
+                        Quote::Double
+                    } else {
+                        unreachable!("Expected string to start with a valid quote prefix")
+                    };
+                    let is_multiline = pattern.contains("\"\"\"") || pattern.contains("'''");
+
+                    let counts = if expect_docstring {
+                        &mut docstring
+                    } else if is_multiline {
+                        &mut multiline
+                    } else {
+                        &mut inline
+                    };
+                    *counts.entry(quote).or_insert(0) += 1;
+                }
+                expect_docstring = false;
+            }
+            _ => expect_docstring = false,
+        }
+    }
+
+    QuotePreferences {
+        inline: modal_profile(inline).unwrap_or_default(),
+        multiline: modal_profile(multiline).unwrap_or_default(),
+        docstring: modal_profile(docstring).unwrap_or_default(),
+    }
+}
+
+/// The line-ending style used in Python source code.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl Default for LineEnding {
+    fn default() -> Self {
+        LineEnding::Lf
+    }
+}
+
+/// Detect the line-ending style of the raw file contents, by counting
+/// `\r\n` pairs versus lone `\n` (and lone `\r`) and returning the dominant
+/// style.
+fn detect_line_ending(contents: &str) -> Option<LineEnding> {
+    let bytes = contents.as_bytes();
+    let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                crlf += 1;
+                i += 1;
+            }
+            b'\r' => cr += 1,
+            b'\n' => lf += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    if lf == 0 && crlf == 0 && cr == 0 {
+        return None;
+    }
+
+    Some(if crlf >= lf && crlf >= cr {
+        LineEnding::CrLf
+    } else if lf >= cr {
+        LineEnding::Lf
+    } else {
+        LineEnding::Cr
+    })
+}
+
+/// Given a tally of observed styles, return a profile for the modal style,
+/// with ties broken in favor of `T::default()`.
+fn modal_profile<T: Default + Eq + std::hash::Hash>(
+    counts: FxHashMap<T, usize>,
+) -> Option<StyleProfile<T>> {
+    let total = counts.values().sum();
+    let max_count = *counts.values().max()?;
+    let ties = counts.values().filter(|count| **count == max_count).count();
+
+    // On a tie, the default may have fewer samples than the tied styles (or
+    // none at all), so its own count is reported rather than `max_count`.
+    let (value, count) = if ties > 1 {
+        let value = T::default();
+        let count = counts.get(&value).copied().unwrap_or(0);
+        (value, count)
+    } else {
+        counts
+            .into_iter()
+            .find(|(_, count)| *count == max_count)?
+    };
+
+    Some(StyleProfile {
+        value,
+        count,
+        total,
+    })
+}
+
+/// Detect the indentation style of the given tokens, by tallying every
+/// `Tok::Indent` and returning the modal style.
+///
+/// Each `Tok::Indent`'s whitespace is cumulative from column zero, so a
+/// nested block's indent is its enclosing block's indent plus one more unit
+/// (e.g. the third nesting level of a 2-space-indented file is `"      "`,
+/// not `"  "`). Tallying that raw, cumulative string would split votes for a
+/// single consistent style across every depth it's used at. Instead, we
+/// track the enclosing indentation on a stack and tally only the increment
+/// added at each `Indent`, so every depth of a 2-space file votes for the
+/// same `"  "` bucket.
+fn detect_indentation(
+    contents: &str,
+    locator: &SourceCodeLocator,
+) -> Option<StyleProfile<Indentation>> {
+    let mut counts: FxHashMap<Indentation, usize> = FxHashMap::default();
+    let mut stack: Vec<String> = vec![String::new()];
     for (_start, tok, end) in lexer::make_tokenizer(contents).flatten() {
-        if let Tok::Indent { .. } = tok {
-            let start = Location::new(end.row(), 0);
-            let whitespace = locator.slice_source_code_range(&Range {
-                location: start,
-                end_location: end,
-            });
-            return Some(Indentation(whitespace.to_string()));
+        match tok {
+            Tok::Indent { .. } => {
+                let start = Location::new(end.row(), 0);
+                let whitespace = locator.slice_source_code_range(&Range {
+                    location: start,
+                    end_location: end,
+                });
+                let enclosing = stack.last().map(String::as_str).unwrap_or("");
+                let increment = whitespace.strip_prefix(enclosing).unwrap_or(whitespace);
+                *counts
+                    .entry(Indentation(increment.to_string()))
+                    .or_insert(0) += 1;
+                stack.push(whitespace.to_string());
+            }
+            Tok::Dedent => {
+                stack.pop();
+                if stack.is_empty() {
+                    stack.push(String::new());
+                }
+            }
+            _ => {}
         }
     }
-    None
+    modal_profile(counts)
 }
 
-/// Detect the quotation style of the given tokens.
-fn detect_quote(contents: &str, locator: &SourceCodeLocator) -> Option<Quote> {
+/// Detect the quotation style of the given tokens, by tallying every
+/// non-triple-quoted `Tok::String` and returning the modal style.
+fn detect_quote(contents: &str, locator: &SourceCodeLocator) -> Option<StyleProfile<Quote>> {
+    let mut counts: FxHashMap<Quote, usize> = FxHashMap::default();
     for (start, tok, end) in lexer::make_tokenizer(contents).flatten() {
         if let Tok::String { .. } = tok {
             let content = locator.slice_source_code_range(&Range {
@@ -110,21 +376,28 @@ fn detect_quote(contents: &str, locator: &SourceCodeLocator) -> Option<Quote> {
                 end_location: end,
             });
             if let Some(pattern) = leading_quote(&content) {
+                if pattern.contains("\"\"\"") || pattern.contains("'''") {
+                    continue;
+                }
                 if pattern.contains('\'') {
-                    return Some(Quote::Single);
+                    *counts.entry(Quote::Single).or_insert(0) += 1;
                 } else if pattern.contains('"') {
-                    return Some(Quote::Double);
+                    *counts.entry(Quote::Double).or_insert(0) += 1;
+                } else {
+                    unreachable!("Expected string to start with a valid quote prefix")
                 }
-                unreachable!("Expected string to start with a valid quote prefix")
             }
         }
     }
-    None
+    modal_profile(counts)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::source_code_style::{detect_indentation, detect_quote, Indentation, Quote};
+    use crate::source_code_style::{
+        detect_indentation, detect_line_ending, detect_quote, detect_quote_preferences,
+        Indentation, LineEnding, Quote,
+    };
     use crate::SourceCodeLocator;
 
     #[test]
@@ -139,7 +412,7 @@ if True:
 "#;
         let locator = SourceCodeLocator::new(contents);
         assert_eq!(
-            detect_indentation(contents, &locator),
+            detect_indentation(contents, &locator).map(|profile| profile.value),
             Some(Indentation("  ".to_string()))
         );
 
@@ -149,7 +422,7 @@ if True:
 "#;
         let locator = SourceCodeLocator::new(contents);
         assert_eq!(
-            detect_indentation(contents, &locator),
+            detect_indentation(contents, &locator).map(|profile| profile.value),
             Some(Indentation("    ".to_string()))
         );
 
@@ -159,7 +432,7 @@ if True:
 "#;
         let locator = SourceCodeLocator::new(contents);
         assert_eq!(
-            detect_indentation(contents, &locator),
+            detect_indentation(contents, &locator).map(|profile| profile.value),
             Some(Indentation("\t".to_string()))
         );
 
@@ -173,6 +446,36 @@ x = (
 "#;
         let locator = SourceCodeLocator::new(contents);
         assert_eq!(detect_indentation(contents, &locator), None);
+
+        // A single stray indent shouldn't win over the dominant style.
+        let contents = r#"
+if True:
+  pass
+if True:
+    pass
+if True:
+    pass
+"#;
+        let locator = SourceCodeLocator::new(contents);
+        assert_eq!(
+            detect_indentation(contents, &locator).map(|profile| profile.value),
+            Some(Indentation("    ".to_string()))
+        );
+
+        // Nested blocks accumulate indentation (e.g. the `pass` below is
+        // four columns in), but every level uses the same 2-space unit, so
+        // that's what should be detected -- not a vote split between "  "
+        // and "    ".
+        let contents = r#"
+if True:
+  if True:
+    pass
+"#;
+        let locator = SourceCodeLocator::new(contents);
+        assert_eq!(
+            detect_indentation(contents, &locator).map(|profile| profile.value),
+            Some(Indentation("  ".to_string()))
+        );
     }
 
     #[test]
@@ -183,11 +486,17 @@ x = (
 
         let contents = r#"x = '1'"#;
         let locator = SourceCodeLocator::new(contents);
-        assert_eq!(detect_quote(contents, &locator), Some(Quote::Single));
+        assert_eq!(
+            detect_quote(contents, &locator).map(|profile| profile.value),
+            Some(Quote::Single)
+        );
 
         let contents = r#"x = "1""#;
         let locator = SourceCodeLocator::new(contents);
-        assert_eq!(detect_quote(contents, &locator), Some(Quote::Double));
+        assert_eq!(
+            detect_quote(contents, &locator).map(|profile| profile.value),
+            Some(Quote::Double)
+        );
 
         let contents = r#"
 def f():
@@ -195,6 +504,60 @@ def f():
     pass
 "#;
         let locator = SourceCodeLocator::new(contents);
-        assert_eq!(detect_quote(contents, &locator), Some(Quote::Double));
+        assert_eq!(detect_quote(contents, &locator), None);
+
+        // A single stray quote shouldn't win over the dominant style.
+        let contents = r#"
+x = '1'
+y = "2"
+z = "3"
+"#;
+        let locator = SourceCodeLocator::new(contents);
+        assert_eq!(
+            detect_quote(contents, &locator).map(|profile| profile.value),
+            Some(Quote::Double)
+        );
+    }
+
+    #[test]
+    fn line_ending() {
+        assert_eq!(detect_line_ending(""), None);
+        assert_eq!(detect_line_ending("x = 1\ny = 2\n"), Some(LineEnding::Lf));
+        assert_eq!(
+            detect_line_ending("x = 1\r\ny = 2\r\n"),
+            Some(LineEnding::CrLf)
+        );
+        assert_eq!(detect_line_ending("x = 1\ry = 2\r"), Some(LineEnding::Cr));
+    }
+
+    #[test]
+    fn quote_preferences() {
+        let contents = r#"
+'''Module docstring.'''
+
+x = '1'
+y = '2'
+z = "3"
+
+a = '''
+multiline
+'''
+
+b = '''
+also multiline
+'''
+
+class Foo:
+    """Class docstring."""
+
+    def bar(self):
+        """Method docstring."""
+        return None
+"#;
+        let locator = SourceCodeLocator::new(contents);
+        let preferences = detect_quote_preferences(contents, &locator);
+        assert_eq!(preferences.docstring.value, Quote::Double);
+        assert_eq!(preferences.inline.value, Quote::Single);
+        assert_eq!(preferences.multiline.value, Quote::Single);
     }
 }
@@ -0,0 +1,347 @@
+//! Safe rewriting of Python string literals between quote styles.
+//!
+//! Given the source text of a single string literal, [`to_quote`] produces
+//! the equivalent literal using a different quote character, decoding and
+//! re-encoding escape sequences as needed so that the resulting literal is
+//! semantically identical to the original.
+
+use crate::source_code_style::Quote;
+
+/// The kind of string literal, which determines how its body is decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// A regular `str` literal, e.g. `"Hello"`.
+    Str,
+    /// A `bytes` literal, e.g. `b"Hello"`.
+    Bytes,
+    /// A raw `str` or `bytes` literal, e.g. `r"Hello"`. Backslashes are
+    /// literal and are never treated as the start of an escape sequence.
+    Raw,
+}
+
+impl Mode {
+    fn from_prefix(prefix: &str) -> Self {
+        if prefix.contains(['r', 'R']) {
+            Mode::Raw
+        } else if prefix.contains(['b', 'B']) {
+            Mode::Bytes
+        } else {
+            Mode::Str
+        }
+    }
+}
+
+/// A single logical character decoded from a string literal's body.
+struct DecodedChar {
+    ch: char,
+    /// Whether `ch` was produced by an escape sequence, rather than
+    /// appearing literally in the source.
+    escaped: bool,
+    /// Whether `ch` came from inside an f-string replacement field
+    /// (`{...}`). Such characters are part of a Python expression, not the
+    /// string's text, and must be copied through unmodified: never escaped
+    /// or unescaped when the surrounding literal's quote changes.
+    in_expr: bool,
+}
+
+/// Decode the body of a string literal (the text between the opening and
+/// closing quotes) into a sequence of logical characters.
+///
+/// Replacement fields in f-strings (`{...}`) are copied through verbatim,
+/// since their contents are Python expressions, not escape sequences.
+fn unescape(body: &str, mode: Mode, is_fstring: bool) -> Vec<DecodedChar> {
+    let mut chars = Vec::new();
+    let mut iter = body.chars().peekable();
+    let mut brace_depth = 0usize;
+
+    while let Some(c) = iter.next() {
+        if is_fstring && (c == '{' || c == '}') {
+            // `{{` and `}}` are escaped literal braces outside an expression.
+            if brace_depth == 0 && iter.peek() == Some(&c) {
+                iter.next();
+                chars.push(DecodedChar {
+                    ch: c,
+                    escaped: true,
+                    in_expr: false,
+                });
+                continue;
+            }
+            if c == '{' {
+                brace_depth += 1;
+            } else {
+                brace_depth = brace_depth.saturating_sub(1);
+            }
+            chars.push(DecodedChar {
+                ch: c,
+                escaped: false,
+                in_expr: false,
+            });
+            continue;
+        }
+
+        if brace_depth > 0 {
+            // Inside a replacement field: copy through verbatim, since this
+            // is a Python expression, not escaped string text.
+            chars.push(DecodedChar {
+                ch: c,
+                escaped: false,
+                in_expr: true,
+            });
+            continue;
+        }
+
+        if mode == Mode::Raw || c != '\\' {
+            chars.push(DecodedChar {
+                ch: c,
+                escaped: false,
+                in_expr: false,
+            });
+            continue;
+        }
+
+        match iter.next() {
+            None => chars.push(DecodedChar {
+                ch: '\\',
+                escaped: false,
+                in_expr: false,
+            }),
+            // Backslash-newline is a line continuation: drop both characters.
+            Some('\n') => {}
+            Some('n') => chars.push(DecodedChar {
+                ch: '\n',
+                escaped: true,
+                in_expr: false,
+            }),
+            Some('t') => chars.push(DecodedChar {
+                ch: '\t',
+                escaped: true,
+                in_expr: false,
+            }),
+            Some('r') => chars.push(DecodedChar {
+                ch: '\r',
+                escaped: true,
+                in_expr: false,
+            }),
+            Some('\\') => chars.push(DecodedChar {
+                ch: '\\',
+                escaped: true,
+                in_expr: false,
+            }),
+            Some(quote @ ('\'' | '"')) => chars.push(DecodedChar {
+                ch: quote,
+                escaped: true,
+                in_expr: false,
+            }),
+            Some('x') => {
+                let hex: String = iter.by_ref().take(2).collect();
+                if let Ok(value) = u8::from_str_radix(&hex, 16) {
+                    chars.push(DecodedChar {
+                        ch: value as char,
+                        escaped: true,
+                        in_expr: false,
+                    });
+                }
+            }
+            Some('u') if mode != Mode::Bytes => {
+                let hex: String = iter.by_ref().take(4).collect();
+                if let Some(ch) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    chars.push(DecodedChar {
+                        ch,
+                        escaped: true,
+                        in_expr: false,
+                    });
+                }
+            }
+            Some('U') if mode != Mode::Bytes => {
+                let hex: String = iter.by_ref().take(8).collect();
+                if let Some(ch) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    chars.push(DecodedChar {
+                        ch,
+                        escaped: true,
+                        in_expr: false,
+                    });
+                }
+            }
+            Some(digit) if digit.is_digit(8) => {
+                let mut octal = String::from(digit);
+                while octal.len() < 3 {
+                    match iter.peek() {
+                        Some(next) if next.is_digit(8) => octal.push(iter.next().unwrap()),
+                        _ => break,
+                    }
+                }
+                if let Ok(value) = u8::from_str_radix(&octal, 8) {
+                    chars.push(DecodedChar {
+                        ch: value as char,
+                        escaped: true,
+                        in_expr: false,
+                    });
+                }
+            }
+            // Not a recognized escape sequence: Python keeps the backslash.
+            Some(other) => {
+                chars.push(DecodedChar {
+                    ch: '\\',
+                    escaped: false,
+                    in_expr: false,
+                });
+                chars.push(DecodedChar {
+                    ch: other,
+                    escaped: false,
+                    in_expr: false,
+                });
+            }
+        }
+    }
+
+    chars
+}
+
+/// Given the source text of a single (non-triple-quoted) Python string
+/// literal, produce the equivalent literal using `target` as its quote
+/// character.
+///
+/// Returns `None` if the literal is triple-quoted (this function never
+/// touches docstring/multiline delimiters), or if it's a raw string whose
+/// body contains the target quote character, which cannot be escaped.
+pub fn to_quote(source: &str, target: &Quote) -> Option<String> {
+    let prefix_len = source.find(['\'', '"'])?;
+    let prefix = &source[..prefix_len];
+    let quoted = &source[prefix_len..];
+    if quoted.starts_with("\"\"\"") || quoted.starts_with("'''") {
+        return None;
+    }
+
+    let body = quoted.get(1..quoted.len() - 1)?;
+
+    let mode = Mode::from_prefix(prefix);
+    let is_fstring = prefix.contains(['f', 'F']);
+
+    let (target_char, other_char) = match target {
+        Quote::Single => ('\'', '"'),
+        Quote::Double => ('"', '\''),
+    };
+
+    if mode == Mode::Raw && body.contains(target_char) {
+        return None;
+    }
+
+    let decoded = unescape(body, mode, is_fstring);
+
+    let mut out = String::with_capacity(source.len());
+    out.push_str(prefix);
+    out.push(target_char);
+    for DecodedChar {
+        ch,
+        escaped,
+        in_expr,
+    } in decoded
+    {
+        if in_expr {
+            // Part of a replacement field's expression, not string text:
+            // never escape or unescape it.
+            out.push(ch);
+        } else if mode == Mode::Bytes && (ch as u32) > 0x7f {
+            // Bytes literals may only contain ASCII characters in source
+            // text, so a decoded high byte (e.g. from a `\xff` or octal
+            // escape) must be re-escaped rather than written out raw.
+            out.push_str(&format!("\\x{:02x}", ch as u32));
+        } else if ch == target_char {
+            out.push('\\');
+            out.push(ch);
+        } else if ch == other_char && escaped {
+            // No longer needs escaping now that it's not the delimiter.
+            out.push(ch);
+        } else if ch == '\\' && mode != Mode::Raw {
+            out.push('\\');
+            out.push('\\');
+        } else if escaped && (ch == '{' || ch == '}') {
+            // A doubled `{{`/`}}` in an f-string stays doubled, or it would
+            // open or close a replacement field.
+            out.push(ch);
+            out.push(ch);
+        } else if escaped && ch.is_control() {
+            // Control characters that came from an escape are escaped again,
+            // since written out raw (e.g. a newline) they would change or
+            // break the literal.
+            match ch {
+                '\n' => out.push_str("\\n"),
+                '\t' => out.push_str("\\t"),
+                '\r' => out.push_str("\\r"),
+                _ => out.push_str(&format!("\\x{:02x}", ch as u32)),
+            }
+        } else {
+            out.push(ch);
+        }
+    }
+    out.push(target_char);
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source_code_style::Quote;
+    use crate::string_rewriter::to_quote;
+
+    #[test]
+    fn single_to_double() {
+        assert_eq!(to_quote(r#"'Hello'"#, &Quote::Double), Some(r#""Hello""#.to_string()));
+    }
+
+    #[test]
+    fn unescapes_no_longer_needed_quote() {
+        assert_eq!(to_quote(r#"'it\'s'"#, &Quote::Double), Some(r#""it's""#.to_string()));
+    }
+
+    #[test]
+    fn escapes_newly_conflicting_quote() {
+        assert_eq!(
+            to_quote(r#"'it\'s "fun"'"#, &Quote::Double),
+            Some(r#""it's \"fun\"""#.to_string())
+        );
+    }
+
+    #[test]
+    fn preserves_prefix() {
+        assert_eq!(to_quote(r#"b'Hello'"#, &Quote::Double), Some(r#"b"Hello""#.to_string()));
+    }
+
+    #[test]
+    fn refuses_raw_string_with_target_quote() {
+        assert_eq!(to_quote(r#"r'it\"s'"#, &Quote::Double), None);
+    }
+
+    #[test]
+    fn refuses_triple_quoted() {
+        assert_eq!(to_quote(r#"'''Hello'''"#, &Quote::Double), None);
+    }
+
+    #[test]
+    fn preserves_fstring_replacement_field() {
+        assert_eq!(
+            to_quote(r#"f'it is {x!r}'"#, &Quote::Double),
+            Some(r#"f"it is {x!r}""#.to_string())
+        );
+    }
+
+    #[test]
+    fn reescapes_high_bytes_in_bytes_literal() {
+        // `ÿ` is not valid in the source text of a bytes literal, so the
+        // decoded high byte must come back out as a `\xHH` escape, not the
+        // raw (non-ASCII) character it decodes to.
+        assert_eq!(to_quote(r#"b'\xff'"#, &Quote::Double), Some(r#"b"\xff""#.to_string()));
+        assert_eq!(to_quote(r#"b'\x80'"#, &Quote::Double), Some(r#"b"\x80""#.to_string()));
+    }
+
+    #[test]
+    fn does_not_escape_quotes_inside_replacement_field() {
+        // The `"x"` inside `{...}` is a nested string literal, not part of
+        // the outer literal's text, and must survive untouched even though
+        // it uses the target quote character.
+        assert_eq!(
+            to_quote(r#"f'{foo("x")}'"#, &Quote::Double),
+            Some(r#"f"{foo("x")}""#.to_string())
+        );
+    }
+}